@@ -1,13 +1,16 @@
-use anyhow::{bail, ensure, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use probe_rs::{
     config::{get_target_by_name, search_chips},
-    DebugProbeSelector, Probe, Target,
+    DebugProbeSelector, Probe, Target, WireProtocol,
 };
+use regex::Regex;
 use serde::Deserialize;
 use std::{
+    collections::{HashMap, HashSet},
     convert::TryInto,
-    ffi::OsStr,
+    fmt,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 ///! # DUT Defintions
 ///!
@@ -15,14 +18,84 @@ use std::{
 ///! which are used by the tester.
 ///!
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 struct RawDutDefinition {
     chip: String,
     /// Selector for the debug probe to be used.
     /// See [probe_rs::DebugProbeSelector].
     probe_selector: String,
 
+    /// Deprecated single-entry alias for `flash_tests`.
     flash_test_binary: Option<String>,
+
+    /// Test artifacts to flash onto the DUT and verify.
+    #[serde(default)]
+    flash_tests: Vec<RawFlashTest>,
+
+    /// Golden file with the expected captured output of the flash tests.
+    expected_output: Option<String>,
+
+    /// Regex -> replacement pairs applied, in order, to captured output
+    /// before comparing it to `expected_output`.
+    #[serde(default)]
+    normalize: Vec<(String, String)>,
+
+    /// Base protocol/speed/reset settings, used as-is if `revisions` is empty,
+    /// or as the defaults each named revision below is merged onto.
+    #[serde(flatten)]
+    settings: RevisionSettings,
+
+    /// Names of the revisions this definition should be expanded into. Each
+    /// name must have a matching top-level table with the same name, which
+    /// may override any of the base settings.
+    revisions: Option<Vec<String>>,
+
+    /// Per-revision overrides, keyed by revision name.
+    #[serde(flatten)]
+    revision_tables: HashMap<String, RevisionSettings>,
+
+    /// If non-empty, this definition only applies on these hosts (as
+    /// reported by [`std::env::consts::OS`]).
+    #[serde(default)]
+    only_hosts: Vec<String>,
+
+    /// This definition is skipped on these hosts (as reported by
+    /// [`std::env::consts::OS`]).
+    #[serde(default)]
+    ignore_hosts: Vec<String>,
+
+    /// If non-empty, this definition only applies on these architectures (as
+    /// reported by [`std::env::consts::ARCH`]).
+    #[serde(default)]
+    only_arch: Vec<String>,
+
+    /// This definition is skipped on these architectures (as reported by
+    /// [`std::env::consts::ARCH`]).
+    #[serde(default)]
+    ignore_arch: Vec<String>,
+
+    /// Minimum probe firmware version required to run this definition.
+    needs_probe_firmware: Option<String>,
+}
+
+/// Protocol/speed/reset settings which can be declared at the top level of a
+/// DUT definition, and overridden per revision.
+#[derive(Debug, Deserialize, Default, Clone)]
+struct RevisionSettings {
+    protocol: Option<String>,
+    speed_khz: Option<u32>,
+    connect_under_reset: Option<bool>,
+}
+
+impl RevisionSettings {
+    /// Merge `self` as the base settings with `overrides` taking precedence.
+    fn merged_with(&self, overrides: &RevisionSettings) -> RevisionSettings {
+        RevisionSettings {
+            protocol: overrides.protocol.clone().or_else(|| self.protocol.clone()),
+            speed_khz: overrides.speed_khz.or(self.speed_khz),
+            connect_under_reset: overrides.connect_under_reset.or(self.connect_under_reset),
+        }
+    }
 }
 
 impl RawDutDefinition {
@@ -36,11 +109,75 @@ impl RawDutDefinition {
     }
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawFlashTest {
+    path: String,
+    format: Option<String>,
+    load_address: Option<u64>,
+    expected_marker: Option<String>,
+}
+
+/// A single flash-test artifact and its load metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlashTest {
+    /// Path to the image to flash, resolved relative to the source file.
+    pub path: PathBuf,
+
+    /// Format of `path`. If not given, it is inferred from the file
+    /// extension by the flash loader.
+    pub format: Option<FlashFormat>,
+
+    /// Address to load the image at. Required when `format` is
+    /// [`FlashFormat::Bin`], since raw binaries carry no load address.
+    pub load_address: Option<u64>,
+
+    /// String to look for in the captured RTT/semihosting output after
+    /// flashing, for this artifact to be considered passing.
+    pub expected_marker: Option<String>,
+}
+
+/// Format of a [`FlashTest`] artifact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashFormat {
+    Elf,
+    Bin,
+    Hex,
+    Ihex,
+}
+
+impl FromStr for FlashFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "elf" => Ok(FlashFormat::Elf),
+            "bin" => Ok(FlashFormat::Bin),
+            "hex" => Ok(FlashFormat::Hex),
+            "ihex" => Ok(FlashFormat::Ihex),
+            other => bail!(
+                "Unknown flash format '{}', expected one of elf, bin, hex, ihex",
+                other
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub enum DefinitionSource {
     File(PathBuf),
     Cli,
 }
 
+impl fmt::Display for DefinitionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionSource::File(path) => write!(f, "{}", path.display()),
+            DefinitionSource::Cli => write!(f, "<cli>"),
+        }
+    }
+}
+
 pub struct DutDefinition {
     pub chip: Target,
 
@@ -48,18 +185,96 @@ pub struct DutDefinition {
     /// See [probe_rs::DebugProbeSelector].
     pub probe_selector: DebugProbeSelector,
 
-    /// Path to a binary which can be used to test
-    /// flashing for the DUT.     
-    pub flash_test_binary: Option<PathBuf>,
+    /// Test artifacts to flash onto the DUT and verify.
+    pub flash_tests: Vec<FlashTest>,
+
+    /// Path to a golden file with the expected captured output of the flash
+    /// tests.
+    pub expected_output: Option<PathBuf>,
+
+    /// Regex -> replacement pairs applied, in order, to captured output
+    /// before comparing it to `expected_output`. Used to mask volatile data
+    /// such as timestamps, addresses, or serial numbers.
+    pub normalize: Vec<(String, String)>,
+
+    /// Protocol to use when connecting to the chip.
+    pub protocol: Option<WireProtocol>,
+
+    /// Protocol speed, in kHz.
+    pub speed_khz: Option<u32>,
+
+    /// Whether to connect to the chip while keeping it in reset.
+    pub connect_under_reset: bool,
+
+    /// Name of the revision this definition was expanded from, if any.
+    pub revision_name: Option<String>,
+
+    /// If non-empty, this definition only applies on these hosts.
+    pub only_hosts: Vec<String>,
+
+    /// This definition is skipped on these hosts.
+    pub ignore_hosts: Vec<String>,
+
+    /// If non-empty, this definition only applies on these architectures.
+    pub only_arch: Vec<String>,
+
+    /// This definition is skipped on these architectures.
+    pub ignore_arch: Vec<String>,
+
+    /// Minimum probe firmware version required to run this definition.
+    pub needs_probe_firmware: Option<String>,
 
     /// Source of the DUT definition.
     pub source: DefinitionSource,
 }
 
+/// Reason why a [`DutDefinition`] is not applicable in the current
+/// environment, returned by [`DutDefinition::is_applicable`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The current host is not in `only_hosts`.
+    HostNotAllowed { host: &'static str },
+    /// The current host is listed in `ignore_hosts`.
+    HostIgnored { host: &'static str },
+    /// The current architecture is not in `only_arch`.
+    ArchNotAllowed { arch: &'static str },
+    /// The current architecture is listed in `ignore_arch`.
+    ArchIgnored { arch: &'static str },
+    /// The attached probe's firmware is older than `needs_probe_firmware`.
+    ProbeFirmwareTooOld { required: String, actual: String },
+    /// `needs_probe_firmware` is set but the probe didn't report a version.
+    ProbeFirmwareUnknown { required: String },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::HostNotAllowed { host } => {
+                write!(f, "host '{}' is not in 'only_hosts'", host)
+            }
+            SkipReason::HostIgnored { host } => write!(f, "host '{}' is in 'ignore_hosts'", host),
+            SkipReason::ArchNotAllowed { arch } => {
+                write!(f, "arch '{}' is not in 'only_arch'", arch)
+            }
+            SkipReason::ArchIgnored { arch } => write!(f, "arch '{}' is in 'ignore_arch'", arch),
+            SkipReason::ProbeFirmwareTooOld { required, actual } => write!(
+                f,
+                "probe firmware '{}' is older than the required '{}'",
+                actual, required
+            ),
+            SkipReason::ProbeFirmwareUnknown { required } => write!(
+                f,
+                "probe did not report a firmware version, but '{}' is required",
+                required
+            ),
+        }
+    }
+}
+
 impl DutDefinition {
     /// Collect all DUT definitions from a direcotry.
     ///
-    /// This will try to parse all TOML files in the given directory
+    /// This will try to parse all TOML files in the given directory tree
     /// into DUT definitions.
     ///
     /// For TOML files which do not contain a valid DUT definition,
@@ -70,40 +285,115 @@ impl DutDefinition {
 
         ensure!(
             directory.is_dir(),
-            "Unable to collect target definitions from path '{}'. Path is not a directory.",
+            "Path '{}' is not a directory",
             directory.display()
         );
 
+        let pattern = directory.join("**/*.toml");
+
+        let pattern = pattern
+            .to_str()
+            .ok_or_else(|| anyhow!("Path '{}' is not valid UTF-8", directory.display()))?;
+
+        DutDefinition::collect_glob(pattern, &[])
+    }
+
+    /// Collect all DUT definitions matching the given glob `pattern`.
+    ///
+    /// Every match is parsed via [`DutDefinition::from_file`] unless its path
+    /// matches one of the glob patterns in `ignore`, in which case it is
+    /// skipped. This allows a lab to spread its definitions across nested
+    /// folders (e.g. `boards/**/*.toml`) and exclude vendored or
+    /// work-in-progress definitions.
+    pub fn collect_glob(pattern: &str, ignore: &[String]) -> Result<Vec<DutDefinition>> {
+        let ignore_patterns = ignore
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .with_context(|| format!("Invalid ignore pattern '{}'", pattern))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         let mut definitions = Vec::new();
 
-        for file in directory.read_dir()? {
-            let file_path = file?.path();
+        for entry in
+            glob::glob(pattern).with_context(|| format!("Invalid glob pattern '{}'", pattern))?
+        {
+            let file_path = entry?;
 
-            // Ignore files without .toml ending
-            if file_path.extension() != Some(OsStr::new("toml")) {
+            if ignore_patterns
+                .iter()
+                .any(|ignore| ignore.matches_path(&file_path))
+            {
                 log::debug!(
-                    "Skipping file {}, does not end with .toml",
+                    "Skipping file {}, matches an ignore pattern",
                     file_path.display(),
                 );
                 continue;
             }
 
-            let definition = DutDefinition::from_file(&file_path)
+            let parsed = DutDefinition::from_file(&file_path)
                 .with_context(|| format!("Failed to parse definition '{}'", file_path.display()))?;
 
-            definitions.push(definition);
+            definitions.extend(parsed);
         }
 
         Ok(definitions)
     }
 
     /// Try to parse a DUT definition from a file.
-    fn from_file(file: &Path) -> Result<Self> {
+    ///
+    /// If the definition declares `revisions`, this returns one
+    /// [`DutDefinition`] per revision.
+    fn from_file(file: &Path) -> Result<Vec<Self>> {
         let raw_definition = RawDutDefinition::from_file(file)?;
 
         DutDefinition::from_raw_definition(raw_definition, file)
     }
 
+    /// Check whether this definition can run in the current environment.
+    ///
+    /// `probe_firmware_version`, if given, is the firmware version reported
+    /// by the attached probe, used to evaluate `needs_probe_firmware`.
+    /// Returns `None` if the definition is applicable, or a [`SkipReason`]
+    /// explaining why it should be skipped instead of run.
+    pub fn is_applicable(&self, probe_firmware_version: Option<&str>) -> Option<SkipReason> {
+        let host = std::env::consts::OS;
+
+        if !self.only_hosts.is_empty() && !self.only_hosts.iter().any(|allowed| allowed == host) {
+            return Some(SkipReason::HostNotAllowed { host });
+        }
+
+        if self.ignore_hosts.iter().any(|ignored| ignored == host) {
+            return Some(SkipReason::HostIgnored { host });
+        }
+
+        let arch = std::env::consts::ARCH;
+
+        if !self.only_arch.is_empty() && !self.only_arch.iter().any(|allowed| allowed == arch) {
+            return Some(SkipReason::ArchNotAllowed { arch });
+        }
+
+        if self.ignore_arch.iter().any(|ignored| ignored == arch) {
+            return Some(SkipReason::ArchIgnored { arch });
+        }
+
+        if let Some(required) = &self.needs_probe_firmware {
+            return match probe_firmware_version {
+                Some(actual) if version_at_least(actual, required) => None,
+                Some(actual) => Some(SkipReason::ProbeFirmwareTooOld {
+                    required: required.clone(),
+                    actual: actual.to_owned(),
+                }),
+                None => Some(SkipReason::ProbeFirmwareUnknown {
+                    required: required.clone(),
+                }),
+            };
+        }
+
+        None
+    }
+
     pub fn open_probe(&self) -> Result<Probe> {
         let probe = Probe::open(self.probe_selector.clone()).with_context(|| {
             format!(
@@ -115,8 +405,12 @@ impl DutDefinition {
         Ok(probe)
     }
 
-    fn from_raw_definition(raw_definition: RawDutDefinition, source_file: &Path) -> Result<Self> {
-        let probe_selector = raw_definition.probe_selector.try_into()?;
+    fn from_raw_definition(
+        raw_definition: RawDutDefinition,
+        source_file: &Path,
+    ) -> Result<Vec<Self>> {
+        let probe_selector: DebugProbeSelector =
+            raw_definition.probe_selector.clone().try_into()?;
 
         let targets = search_chips(&raw_definition.chip)?;
 
@@ -141,30 +435,942 @@ impl DutDefinition {
 
         let target = get_target_by_name(&targets[0])?;
 
-        let flash_test_binary = raw_definition.flash_test_binary.map(PathBuf::from);
+        let flash_tests = resolve_flash_tests(&raw_definition, source_file)?;
+
+        let expected_output =
+            resolve_optional_source_relative_path(raw_definition.expected_output, source_file)?;
+
+        ensure!(
+            raw_definition.revisions.is_some() || raw_definition.revision_tables.is_empty(),
+            "revision tables {:?} defined but no top-level 'revisions' list references them in \
+             '{}'; check that 'revisions' is declared before any [[flash_tests]]/table blocks, \
+             since TOML silently attaches keys written after an array-of-tables to its last entry",
+            raw_definition.revision_tables.keys().collect::<Vec<_>>(),
+            source_file.display()
+        );
+
+        let revisions = match &raw_definition.revisions {
+            Some(names) if !names.is_empty() => names
+                .iter()
+                .map(|name| {
+                    let overrides = raw_definition
+                        .revision_tables
+                        .get(name)
+                        .cloned()
+                        .with_context(|| {
+                            format!(
+                                "Revision '{}' has no matching top-level table in '{}'",
+                                name,
+                                source_file.display()
+                            )
+                        })?;
+
+                    Ok((
+                        Some(name.clone()),
+                        raw_definition.settings.merged_with(&overrides),
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => vec![(None, raw_definition.settings.clone())],
+        };
+
+        revisions
+            .into_iter()
+            .map(|(revision_name, settings)| {
+                let protocol = settings
+                    .protocol
+                    .map(|protocol| {
+                        protocol
+                            .parse()
+                            .map_err(|_| anyhow!("Unknown protocol '{}'", protocol))
+                    })
+                    .transpose()?;
+
+                Ok(Self {
+                    chip: target.clone(),
+                    probe_selector: probe_selector.clone(),
+                    flash_tests: flash_tests.clone(),
+                    expected_output: expected_output.clone(),
+                    normalize: raw_definition.normalize.clone(),
+                    protocol,
+                    speed_khz: settings.speed_khz,
+                    connect_under_reset: settings.connect_under_reset.unwrap_or(false),
+                    revision_name,
+                    only_hosts: raw_definition.only_hosts.clone(),
+                    ignore_hosts: raw_definition.ignore_hosts.clone(),
+                    only_arch: raw_definition.only_arch.clone(),
+                    ignore_arch: raw_definition.ignore_arch.clone(),
+                    needs_probe_firmware: raw_definition.needs_probe_firmware.clone(),
+                    source: DefinitionSource::File(source_file.to_owned()),
+                })
+            })
+            .collect()
+    }
+
+    /// Validate a collected set of [`DutDefinition`]s for selector conflicts
+    /// and duplicate definitions, merging exact duplicates into one entry.
+    ///
+    /// Two definitions claiming the same `probe_selector` but targeting a
+    /// different chip or flash binary fail this check, since running them
+    /// together would nondeterministically attach to the same physical
+    /// probe from two tests. Definitions for the same chip, probe, and flash
+    /// binary that only differ in their revision (`revision_name`,
+    /// `protocol`, `speed_khz`, `connect_under_reset`) are left alone, since
+    /// that's exactly what a file's `revisions` list expands into and they
+    /// are meant to run sequentially against the same physical probe. Exact
+    /// duplicates (same chip, probe, flash binary, and revision) are
+    /// collapsed into a single entry instead, with a warning, since they are
+    /// harmless but likely a copy-paste mistake.
+    pub fn validate_set(defs: Vec<DutDefinition>) -> Result<Vec<DutDefinition>> {
+        let mut by_selector: HashMap<String, Vec<usize>> = HashMap::new();
 
-        let flash_test_binary = match flash_test_binary {
-            Some(path) => {
-                if path.is_absolute() {
-                    Some(path)
-                } else {
-                    // For relative paths, join the path with the location of the source file to create an absolute path.
+        for (index, def) in defs.iter().enumerate() {
+            by_selector
+                .entry(def.probe_selector.to_string())
+                .or_default()
+                .push(index);
+        }
+
+        let mut conflicts = Vec::new();
+        let mut duplicate_indices: HashSet<usize> = HashSet::new();
+
+        for (selector, indices) in &by_selector {
+            for i in 0..indices.len() {
+                for j in (i + 1)..indices.len() {
+                    let (a_index, b_index) = (indices[i], indices[j]);
 
-                    let source_file_directory = source_file.parent().unwrap_or(Path::new("."));
+                    if duplicate_indices.contains(&b_index) {
+                        continue;
+                    }
 
-                    let flash_binary_location = source_file_directory.join(path);
+                    let (a, b) = (&defs[a_index], &defs[b_index]);
 
-                    Some(flash_binary_location.canonicalize()?)
+                    if is_exact_duplicate(a, b) {
+                        log::warn!(
+                            "Duplicate DUT definitions for probe '{}': '{}' and '{}', keeping '{}'",
+                            selector,
+                            a.source,
+                            b.source,
+                            a.source,
+                        );
+                        duplicate_indices.insert(b_index);
+                    } else if is_same_dut(a, b) {
+                        // Same chip/probe/flash binary, different revision:
+                        // this is the intended fan-out of one DUT into
+                        // several revisions sharing a physical probe.
+                    } else {
+                        conflicts.push(format!(
+                            "probe '{}' is claimed by both '{}' and '{}'",
+                            selector, a.source, b.source
+                        ));
+                    }
                 }
             }
-            None => None,
+        }
+
+        ensure!(
+            conflicts.is_empty(),
+            "Found conflicting DUT definitions:\n{}",
+            conflicts.join("\n")
+        );
+
+        Ok(defs
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !duplicate_indices.contains(index))
+            .map(|(_, def)| def)
+            .collect())
+    }
+
+    /// Apply `normalize` to `actual` output captured from a flash test.
+    fn normalize_output(&self, actual: &str) -> Result<String> {
+        let mut normalized = actual.to_owned();
+
+        for (pattern, replacement) in &self.normalize {
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("Invalid normalize pattern '{}'", pattern))?;
+
+            normalized = regex
+                .replace_all(&normalized, replacement.as_str())
+                .into_owned();
+        }
+
+        Ok(normalized)
+    }
+
+    /// Compare `actual` program output (e.g. captured RTT/semihosting output
+    /// from a flash test) against `expected_output`, after applying
+    /// `normalize`.
+    ///
+    /// If `expected_output` is not set, this does nothing. If `bless` is
+    /// `true`, the normalized actual output is written to `expected_output`
+    /// instead of being compared against it.
+    pub fn compare_expected_output(&self, actual: &str, bless: bool) -> Result<()> {
+        let expected_output = match &self.expected_output {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let normalized = self.normalize_output(actual)?;
+
+        if bless {
+            std::fs::write(expected_output, &normalized).with_context(|| {
+                format!(
+                    "Failed to write golden file '{}'",
+                    expected_output.display()
+                )
+            })?;
+
+            return Ok(());
+        }
+
+        let golden = std::fs::read_to_string(expected_output).with_context(|| {
+            format!("Failed to read golden file '{}'", expected_output.display())
+        })?;
+
+        if normalized.lines().eq(golden.lines()) {
+            return Ok(());
+        }
+
+        let diff = similar::TextDiff::from_lines(&golden, &normalized)
+            .unified_diff()
+            .header("expected", "actual")
+            .to_string();
+
+        bail!(
+            "Captured output does not match expected output in '{}':\n{}",
+            expected_output.display(),
+            diff
+        );
+    }
+}
+
+/// Resolve `path` relative to the directory containing `source_file`, the
+/// way `flash_test_binary`/`flash_tests` paths have always been resolved.
+/// Absolute paths are returned unchanged. The resolved path must exist.
+fn resolve_source_relative_path(path: &str, source_file: &Path) -> Result<PathBuf> {
+    let path = PathBuf::from(path);
+
+    if path.is_absolute() {
+        return Ok(path);
+    }
+
+    // For relative paths, join the path with the location of the source file to create an absolute path.
+    let source_file_directory = source_file.parent().unwrap_or(Path::new("."));
+    let location = source_file_directory.join(path);
+
+    Ok(location.canonicalize()?)
+}
+
+/// Resolve `path`, if given, the same way [`resolve_source_relative_path`]
+/// does, except the resolved path is allowed not to exist yet, e.g. a golden
+/// `expected_output` file before the first `--bless` run.
+fn resolve_optional_source_relative_path(
+    path: Option<String>,
+    source_file: &Path,
+) -> Result<Option<PathBuf>> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => return Ok(None),
+    };
+
+    if path.is_absolute() {
+        return Ok(Some(path));
+    }
+
+    let source_file_directory = source_file.parent().unwrap_or(Path::new("."));
+    let location = source_file_directory.join(path);
+
+    match location.canonicalize() {
+        Ok(location) => Ok(Some(location)),
+        Err(_) if !location.exists() => Ok(Some(location)),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Whether `a` and `b` describe the same chip, flash binaries, and
+/// connection settings, and so are redundant rather than conflicting or
+/// distinct revisions. The `probe_selector` is not compared here;
+/// [`DutDefinition::validate_set`] only calls this for definitions sharing
+/// one. See [`DutDefinition::validate_set`] for why revision fields are part
+/// of this comparison.
+fn is_exact_duplicate(a: &DutDefinition, b: &DutDefinition) -> bool {
+    is_same_dut(a, b)
+        && a.revision_name == b.revision_name
+        && a.protocol == b.protocol
+        && a.speed_khz == b.speed_khz
+        && a.connect_under_reset == b.connect_under_reset
+}
+
+/// Whether `a` and `b` describe the same physical chip and flash binaries,
+/// regardless of revision. See [`DutDefinition::validate_set`] for why two
+/// revisions of the same DUT are expected to share a `probe_selector`.
+fn is_same_dut(a: &DutDefinition, b: &DutDefinition) -> bool {
+    a.chip.name == b.chip.name && a.flash_tests == b.flash_tests
+}
+
+/// Resolve a definition's `flash_test_binary` and `flash_tests` into a single
+/// list of [`FlashTest`]s, with `flash_test_binary` (if present) becoming the
+/// first entry.
+fn resolve_flash_tests(
+    raw_definition: &RawDutDefinition,
+    source_file: &Path,
+) -> Result<Vec<FlashTest>> {
+    let mut flash_tests = Vec::new();
+
+    if let Some(path) = &raw_definition.flash_test_binary {
+        log::warn!(
+            "'flash_test_binary' in '{}' is deprecated, use 'flash_tests' instead",
+            source_file.display()
+        );
+
+        flash_tests.push(FlashTest {
+            path: resolve_source_relative_path(path, source_file).with_context(|| {
+                format!("Failed to resolve 'flash_test_binary' path '{}'", path)
+            })?,
+            format: None,
+            load_address: None,
+            expected_marker: None,
+        });
+    }
+
+    for raw_test in &raw_definition.flash_tests {
+        let path = resolve_source_relative_path(&raw_test.path, source_file)
+            .with_context(|| format!("Failed to resolve flash test path '{}'", raw_test.path))?;
+
+        let format = raw_test
+            .format
+            .as_deref()
+            .map(FlashFormat::from_str)
+            .transpose()?;
+
+        ensure!(
+            format != Some(FlashFormat::Bin) || raw_test.load_address.is_some(),
+            "Flash test '{}' uses the 'bin' format, which requires 'load_address'",
+            path.display()
+        );
+
+        flash_tests.push(FlashTest {
+            path,
+            format,
+            load_address: raw_test.load_address,
+            expected_marker: raw_test.expected_marker.clone(),
+        });
+    }
+
+    Ok(flash_tests)
+}
+
+/// Parse a dotted version string (e.g. `"1.12.3"`) into its numeric
+/// components, for ordering comparisons. Non-numeric or missing components
+/// are treated as `0`.
+fn parse_version(version: &str) -> Vec<u32> {
+    version
+        .split('.')
+        .map(|component| component.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Whether `actual` is greater than or equal to `required`, comparing
+/// component-by-component and treating a missing trailing component as `0`
+/// (so `"2.0"` is equal to, not less than, `"2.0.0"`).
+fn version_at_least(actual: &str, required: &str) -> bool {
+    let actual = parse_version(actual);
+    let required = parse_version(required);
+
+    let len = actual.len().max(required.len());
+
+    for i in 0..len {
+        let a = actual.get(i).copied().unwrap_or(0);
+        let r = required.get(i).copied().unwrap_or(0);
+
+        match a.cmp(&r) {
+            std::cmp::Ordering::Less => return false,
+            std::cmp::Ordering::Greater => return true,
+            std::cmp::Ordering::Equal => continue,
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Any chip present in probe-rs's built-in target database works here;
+    /// which one is irrelevant to what these tests exercise.
+    fn test_chip() -> Target {
+        get_target_by_name("STM32F401RETx")
+            .expect("built-in target database must contain this chip")
+    }
+
+    /// Create a fresh, canonicalized temp directory for a test that needs to
+    /// exercise real file/TOML parsing rather than hand-built Rust structs.
+    fn temp_dir(name: &str) -> PathBuf {
+        static COUNTER: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+        let id = COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let dir = std::env::temp_dir().join(format!(
+            "probe-rs-smoke-tester-test-{}-{}-{}",
+            std::process::id(),
+            name,
+            id
+        ));
+
+        std::fs::create_dir_all(&dir).expect("failed to create temp dir");
+        dir.canonicalize().expect("failed to canonicalize temp dir")
+    }
+
+    /// Write `contents` to `dir/name` and return its path.
+    fn write_temp_file(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).expect("failed to write temp file");
+        path
+    }
+
+    fn test_def(
+        source: &str,
+        revision_name: Option<&str>,
+        speed_khz: Option<u32>,
+    ) -> DutDefinition {
+        DutDefinition {
+            chip: test_chip(),
+            probe_selector: "1366:0101"
+                .to_owned()
+                .try_into()
+                .expect("valid probe selector"),
+            flash_tests: vec![FlashTest {
+                path: PathBuf::from("test.elf"),
+                format: None,
+                load_address: None,
+                expected_marker: None,
+            }],
+            expected_output: None,
+            normalize: Vec::new(),
+            protocol: None,
+            speed_khz,
+            connect_under_reset: false,
+            revision_name: revision_name.map(str::to_owned),
+            only_hosts: Vec::new(),
+            ignore_hosts: Vec::new(),
+            only_arch: Vec::new(),
+            ignore_arch: Vec::new(),
+            needs_probe_firmware: None,
+            source: DefinitionSource::File(PathBuf::from(source)),
+        }
+    }
+
+    #[test]
+    fn validate_set_keeps_distinct_revisions_of_the_same_dut() {
+        // Same file expanded into two revisions of one DUT: same chip,
+        // probe, and flash_tests, different protocol speed.
+        let defs = vec![
+            test_def("board.toml", Some("swd_fast"), Some(4000)),
+            test_def("board.toml", Some("swd_slow"), Some(100)),
+        ];
+
+        let result =
+            DutDefinition::validate_set(defs).expect("same-DUT revisions must not conflict");
+
+        assert_eq!(result.len(), 2);
+    }
+
+    #[test]
+    fn validate_set_merges_exact_duplicates() {
+        let defs = vec![
+            test_def("board.toml", None, None),
+            test_def("board.toml", None, None),
+        ];
+
+        let result = DutDefinition::validate_set(defs).expect("exact duplicates must merge");
+
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn validate_set_rejects_conflicting_definitions() {
+        let mut other = test_def("other.toml", None, None);
+        other.flash_tests[0].path = PathBuf::from("other.elf");
+
+        let defs = vec![test_def("board.toml", None, None), other];
+
+        assert!(DutDefinition::validate_set(defs).is_err());
+    }
+
+    #[test]
+    fn version_at_least_handles_differing_segment_counts() {
+        assert!(version_at_least("2.0.0", "2.0"));
+        assert!(version_at_least("2.0", "2.0.0"));
+        assert!(!version_at_least("1.9", "2.0.0"));
+        assert!(version_at_least("2.1", "2.0.0"));
+    }
+
+    #[test]
+    fn collect_glob_parses_real_toml_and_applies_ignore_patterns() {
+        let dir = temp_dir("collect_glob");
+        write_temp_file(&dir, "test.elf", "");
+
+        write_temp_file(
+            &dir,
+            "keep.toml",
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0101"
+                flash_tests = [{ path = "test.elf" }]
+            "#,
+        );
+        write_temp_file(
+            &dir,
+            "skip.toml",
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0102"
+                flash_tests = [{ path = "test.elf" }]
+            "#,
+        );
+
+        let pattern = dir.join("*.toml");
+        let pattern = pattern.to_str().expect("valid UTF-8 path");
+        let ignore = vec![dir.join("skip.toml").to_str().unwrap().to_owned()];
+
+        let defs = DutDefinition::collect_glob(pattern, &ignore)
+            .expect("collect_glob should parse real TOML files");
+
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].probe_selector.to_string(), "1366:0101");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn from_raw_definition_expands_revisions_declared_before_flash_tests_array() {
+        let dir = temp_dir("revisions_fan_out");
+        let flash_path = write_temp_file(&dir, "test.elf", "");
+
+        let toml_str = format!(
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0101"
+                revisions = ["swd_fast", "swd_slow"]
+
+                [[flash_tests]]
+                path = "{path}"
+
+                [swd_fast]
+                speed_khz = 4000
+
+                [swd_slow]
+                speed_khz = 100
+            "#,
+            path = flash_path.display()
+        );
+
+        let raw: RawDutDefinition = toml::from_str(&toml_str).expect("valid TOML");
+        let defs = DutDefinition::from_raw_definition(raw, &dir.join("board.toml"))
+            .expect("revisions declared before the flash_tests array must still expand");
+
+        assert_eq!(defs.len(), 2);
+        assert_eq!(defs[0].revision_name.as_deref(), Some("swd_fast"));
+        assert_eq!(defs[0].speed_khz, Some(4000));
+        assert_eq!(defs[1].revision_name.as_deref(), Some("swd_slow"));
+        assert_eq!(defs[1].speed_khz, Some(100));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn from_raw_definition_rejects_revisions_declared_after_flash_tests_array() {
+        let dir = temp_dir("revisions_misplaced");
+        let flash_path = write_temp_file(&dir, "test.elf", "");
+
+        // 'revisions' written after the [[flash_tests]] array-of-tables
+        // block is attached by TOML to the last flash_tests entry instead of
+        // the top-level definition; 'deny_unknown_fields' on RawFlashTest
+        // must turn that into a parse error instead of a silently ignored
+        // field.
+        let toml_str = format!(
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0101"
+
+                [[flash_tests]]
+                path = "{path}"
+
+                revisions = ["swd_fast", "swd_slow"]
+
+                [swd_fast]
+                speed_khz = 4000
+
+                [swd_slow]
+                speed_khz = 100
+            "#,
+            path = flash_path.display()
+        );
+
+        let error = toml::from_str::<RawDutDefinition>(&toml_str)
+            .expect_err("misplaced 'revisions' key must be rejected, not silently dropped");
+
+        assert!(error.to_string().contains("revisions"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn from_raw_definition_rejects_orphaned_revision_tables_without_revisions_list() {
+        let dir = temp_dir("revisions_missing_list");
+        let flash_path = write_temp_file(&dir, "test.elf", "");
+
+        // No top-level 'revisions' list references the [swd_fast] table at
+        // all, e.g. because the author forgot to add it.
+        let toml_str = format!(
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0101"
+                flash_tests = [{{ path = "{path}" }}]
+
+                [swd_fast]
+                speed_khz = 4000
+            "#,
+            path = flash_path.display()
+        );
+
+        let raw: RawDutDefinition = toml::from_str(&toml_str).expect("valid TOML");
+
+        // `DutDefinition` embeds `probe_rs::Target`, which isn't `Debug`, so
+        // `Result::expect_err` (which requires the `Ok` type to be `Debug`)
+        // can't be used here; match on the error directly instead.
+        let error = match DutDefinition::from_raw_definition(raw, &dir.join("board.toml")) {
+            Err(error) => error,
+            Ok(_) => {
+                panic!("an orphaned revision table without a 'revisions' list must be rejected")
+            }
+        };
+
+        assert!(error.to_string().contains("swd_fast"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn from_raw_definition_parses_expected_output_and_normalize() {
+        let dir = temp_dir("expected_output");
+        let flash_path = write_temp_file(&dir, "test.elf", "");
+
+        let toml_str = format!(
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0101"
+                flash_tests = [{{ path = "{path}" }}]
+                expected_output = "golden.txt"
+                normalize = [["\\d+ms", "<MS>"], ["0x[0-9a-f]+", "<ADDR>"]]
+            "#,
+            path = flash_path.display()
+        );
+
+        let raw: RawDutDefinition = toml::from_str(&toml_str).expect("valid TOML");
+        let defs = DutDefinition::from_raw_definition(raw, &dir.join("board.toml"))
+            .expect("definition should parse");
+
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].expected_output, Some(dir.join("golden.txt")));
+        assert_eq!(
+            defs[0].normalize,
+            vec![
+                (r"\d+ms".to_owned(), "<MS>".to_owned()),
+                (r"0x[0-9a-f]+".to_owned(), "<ADDR>".to_owned()),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn from_raw_definition_parses_capability_gating_fields() {
+        let dir = temp_dir("capability_gating");
+        let flash_path = write_temp_file(&dir, "test.elf", "");
+
+        let toml_str = format!(
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0101"
+                flash_tests = [{{ path = "{path}" }}]
+                only_hosts = ["linux"]
+                ignore_hosts = ["windows"]
+                only_arch = ["x86_64"]
+                ignore_arch = ["arm"]
+                needs_probe_firmware = "1.12.0"
+            "#,
+            path = flash_path.display()
+        );
+
+        let raw: RawDutDefinition = toml::from_str(&toml_str).expect("valid TOML");
+        let defs = DutDefinition::from_raw_definition(raw, &dir.join("board.toml"))
+            .expect("definition should parse");
+
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].only_hosts, vec!["linux".to_owned()]);
+        assert_eq!(defs[0].ignore_hosts, vec!["windows".to_owned()]);
+        assert_eq!(defs[0].only_arch, vec!["x86_64".to_owned()]);
+        assert_eq!(defs[0].ignore_arch, vec!["arm".to_owned()]);
+        assert_eq!(defs[0].needs_probe_firmware.as_deref(), Some("1.12.0"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn from_raw_definition_orders_deprecated_alias_before_flash_tests_array() {
+        let dir = temp_dir("flash_tests_alias");
+        let legacy_path = write_temp_file(&dir, "legacy.elf", "");
+        let first_path = write_temp_file(&dir, "first.bin", "");
+        let second_path = write_temp_file(&dir, "second.elf", "");
+
+        let toml_str = format!(
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0101"
+                flash_test_binary = "{legacy}"
+
+                [[flash_tests]]
+                path = "{first}"
+                format = "bin"
+                load_address = 134217728
+                expected_marker = "FIRST OK"
+
+                [[flash_tests]]
+                path = "{second}"
+            "#,
+            legacy = legacy_path.display(),
+            first = first_path.display(),
+            second = second_path.display()
+        );
+
+        let raw: RawDutDefinition = toml::from_str(&toml_str).expect("valid TOML");
+        let defs = DutDefinition::from_raw_definition(raw, &dir.join("board.toml"))
+            .expect("definition should parse");
+
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].flash_tests.len(), 3);
+        assert_eq!(defs[0].flash_tests[0].path, legacy_path);
+        assert_eq!(defs[0].flash_tests[1].path, first_path);
+        assert_eq!(defs[0].flash_tests[1].format, Some(FlashFormat::Bin));
+        assert_eq!(defs[0].flash_tests[1].load_address, Some(0x08000000));
+        assert_eq!(
+            defs[0].flash_tests[1].expected_marker.as_deref(),
+            Some("FIRST OK")
+        );
+        assert_eq!(defs[0].flash_tests[2].path, second_path);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn collect_discovers_definitions_recursively_through_collect() {
+        let dir = temp_dir("collect_recursive");
+        let nested = dir.join("boards").join("nested");
+        std::fs::create_dir_all(&nested).expect("failed to create nested dir");
+
+        write_temp_file(&dir, "test.elf", "");
+        write_temp_file(
+            &dir,
+            "top.toml",
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0101"
+                flash_tests = [{ path = "test.elf" }]
+            "#,
+        );
+        write_temp_file(
+            &nested,
+            "nested.toml",
+            r#"
+                chip = "STM32F401RETx"
+                probe_selector = "1366:0102"
+                flash_tests = [{ path = "../../test.elf" }]
+            "#,
+        );
+
+        let defs =
+            DutDefinition::collect(&dir).expect("collect should recurse into subdirectories");
+
+        let mut selectors: Vec<String> = defs
+            .iter()
+            .map(|def| def.probe_selector.to_string())
+            .collect();
+        selectors.sort();
+
+        assert_eq!(
+            selectors,
+            vec!["1366:0101".to_owned(), "1366:0102".to_owned()]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn is_applicable_respects_only_hosts() {
+        let host = std::env::consts::OS;
+
+        let allowed = DutDefinition {
+            only_hosts: vec![host.to_owned()],
+            ..test_def("board.toml", None, None)
+        };
+        assert_eq!(allowed.is_applicable(None), None);
+
+        let disallowed = DutDefinition {
+            only_hosts: vec!["not-a-real-host".to_owned()],
+            ..test_def("board.toml", None, None)
+        };
+        assert_eq!(
+            disallowed.is_applicable(None),
+            Some(SkipReason::HostNotAllowed { host })
+        );
+    }
+
+    #[test]
+    fn is_applicable_respects_ignore_hosts() {
+        let host = std::env::consts::OS;
+
+        let ignored = DutDefinition {
+            ignore_hosts: vec![host.to_owned()],
+            ..test_def("board.toml", None, None)
+        };
+        assert_eq!(
+            ignored.is_applicable(None),
+            Some(SkipReason::HostIgnored { host })
+        );
+    }
+
+    #[test]
+    fn is_applicable_respects_only_arch() {
+        let arch = std::env::consts::ARCH;
+
+        let allowed = DutDefinition {
+            only_arch: vec![arch.to_owned()],
+            ..test_def("board.toml", None, None)
         };
+        assert_eq!(allowed.is_applicable(None), None);
+
+        let disallowed = DutDefinition {
+            only_arch: vec!["not-a-real-arch".to_owned()],
+            ..test_def("board.toml", None, None)
+        };
+        assert_eq!(
+            disallowed.is_applicable(None),
+            Some(SkipReason::ArchNotAllowed { arch })
+        );
+    }
+
+    #[test]
+    fn is_applicable_respects_ignore_arch() {
+        let arch = std::env::consts::ARCH;
+
+        let ignored = DutDefinition {
+            ignore_arch: vec![arch.to_owned()],
+            ..test_def("board.toml", None, None)
+        };
+        assert_eq!(
+            ignored.is_applicable(None),
+            Some(SkipReason::ArchIgnored { arch })
+        );
+    }
+
+    #[test]
+    fn is_applicable_compares_required_probe_firmware() {
+        let def = DutDefinition {
+            needs_probe_firmware: Some("2.0.0".to_owned()),
+            ..test_def("board.toml", None, None)
+        };
+
+        assert_eq!(def.is_applicable(Some("2.0.0")), None);
+        assert_eq!(def.is_applicable(Some("2.1.0")), None);
+        assert_eq!(
+            def.is_applicable(Some("1.9.0")),
+            Some(SkipReason::ProbeFirmwareTooOld {
+                required: "2.0.0".to_owned(),
+                actual: "1.9.0".to_owned(),
+            })
+        );
+        assert_eq!(
+            def.is_applicable(None),
+            Some(SkipReason::ProbeFirmwareUnknown {
+                required: "2.0.0".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn is_applicable_returns_none_when_no_gating_fields_are_set() {
+        let def = test_def("board.toml", None, None);
+
+        assert_eq!(def.is_applicable(None), None);
+        assert_eq!(def.is_applicable(Some("1.0.0")), None);
+    }
+
+    #[test]
+    fn compare_expected_output_passes_when_normalized_actual_matches_golden() {
+        let dir = temp_dir("compare_expected_output_match");
+        let golden_path = write_temp_file(&dir, "golden.txt", "boot at <ADDR>\nREADY\n");
+
+        let def = DutDefinition {
+            expected_output: Some(golden_path),
+            normalize: vec![(r"0x[0-9a-f]+".to_owned(), "<ADDR>".to_owned())],
+            ..test_def("board.toml", None, None)
+        };
+
+        def.compare_expected_output("boot at 0x08000000\nREADY\n", false)
+            .expect("normalized actual should match the golden file");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn compare_expected_output_fails_with_a_unified_diff_on_mismatch() {
+        let dir = temp_dir("compare_expected_output_mismatch");
+        let golden_path = write_temp_file(&dir, "golden.txt", "READY\n");
+
+        let def = DutDefinition {
+            expected_output: Some(golden_path),
+            ..test_def("board.toml", None, None)
+        };
+
+        let error = def
+            .compare_expected_output("NOT READY\n", false)
+            .expect_err("mismatched output must fail");
+
+        let message = error.to_string();
+        assert!(message.contains("-READY"));
+        assert!(message.contains("+NOT READY"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn compare_expected_output_bless_overwrites_golden_with_normalized_actual() {
+        let dir = temp_dir("compare_expected_output_bless");
+        let golden_path = write_temp_file(&dir, "golden.txt", "stale\n");
+
+        let def = DutDefinition {
+            expected_output: Some(golden_path.clone()),
+            normalize: vec![(r"0x[0-9a-f]+".to_owned(), "<ADDR>".to_owned())],
+            ..test_def("board.toml", None, None)
+        };
+
+        def.compare_expected_output("boot at 0x08000000\n", true)
+            .expect("bless should succeed");
+
+        let blessed =
+            std::fs::read_to_string(&golden_path).expect("golden file should be rewritten");
+        assert_eq!(blessed, "boot at <ADDR>\n");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn compare_expected_output_is_a_no_op_without_expected_output() {
+        let def = test_def("board.toml", None, None);
 
-        Ok(Self {
-            chip: target,
-            probe_selector,
-            flash_test_binary,
-            source: DefinitionSource::File(source_file.to_owned()),
-        })
+        def.compare_expected_output("anything", false)
+            .expect("no expected_output means nothing to compare");
     }
 }